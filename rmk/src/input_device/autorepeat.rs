@@ -0,0 +1,302 @@
+//! Built-in key autorepeat processor for RMK
+//!
+//! This module provides [`Autorepeat`], an [`InputProcessor`] stage that re-emits a held key at a
+//! steady rate, the same capability dedicated "autorepeater" stages provide in other input
+//! pipelines. Without it, users wanting autorepeat must hand-roll the timing themselves; as a
+//! drop-in [`ProcessorChain`](super::processor_chain::ProcessorChain) stage it is reusable across
+//! matrix keys, rotary encoders, or any other device whose events implement [`RepeatableEvent`].
+//!
+//! Driving autorepeat needs two things running concurrently over the same state: [`Autorepeat`]
+//! itself, observing key-downs/key-ups as a chain stage, and a timer loop that wakes up and
+//! re-emits the active key. Since both need mutable access to the same scheduler while being
+//! polled by two different tasks (the chain, and whatever joins the timer), the state is split out
+//! into a shared [`AutorepeatState`] behind an `embassy_sync` [`Mutex`], with [`Autorepeat`] and
+//! [`AutorepeatTimer`] each holding a reference to it. See [`Autorepeat::new`] for how to build the
+//! pair.
+
+use embassy_futures::select::{select, Either};
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::channel::Sender;
+use embassy_sync::mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer};
+
+use super::processor_chain::NoReport;
+use super::scheduler::EventScheduler;
+use super::{InputProcessor, ProcessorStatus};
+
+/// An event that [`Autorepeat`] can recognize and repeat.
+pub trait RepeatableEvent: Clone {
+    /// A stable identity for the physical key (or other control) this event refers to, e.g. a
+    /// matrix position.
+    type KeyId: PartialEq + Clone;
+
+    /// The key this event refers to.
+    fn key_id(&self) -> Self::KeyId;
+
+    /// Whether this event is the key going down (`true`) or up (`false`).
+    fn is_press(&self) -> bool;
+}
+
+/// The mutable state shared between [`Autorepeat`] and its [`AutorepeatTimer`].
+struct AutorepeatState<E: RepeatableEvent> {
+    scheduler: EventScheduler<E, 1>,
+    active: Option<E::KeyId>,
+}
+
+impl<E: RepeatableEvent> AutorepeatState<E> {
+    const fn new() -> Self {
+        Self {
+            scheduler: EventScheduler::new(),
+            active: None,
+        }
+    }
+}
+
+/// State shared between an [`Autorepeat`] stage and its [`AutorepeatTimer`], guarding it with a
+/// `Mutex` (rather than giving each a separate `&mut` over the same struct) since they're driven
+/// by two different, concurrently-polled futures.
+pub struct AutorepeatShared<M: RawMutex, E: RepeatableEvent> {
+    state: Mutex<M, AutorepeatState<E>>,
+    /// Wakes [`AutorepeatTimer::run`] when it's idling with nothing scheduled and `Autorepeat`
+    /// schedules the initial delay for a new press.
+    wake: Signal<M, ()>,
+}
+
+impl<M: RawMutex, E: RepeatableEvent> AutorepeatShared<M, E> {
+    /// Create shared state for a new autorepeat pair. Build the pair itself with
+    /// [`Autorepeat::new`].
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(AutorepeatState::new()),
+            wake: Signal::new(),
+        }
+    }
+}
+
+impl<M: RawMutex, E: RepeatableEvent> Default for AutorepeatShared<M, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Autorepeats the most recently pressed repeat-eligible key.
+///
+/// On a key-down, `Autorepeat` starts a timer for `initial_delay`; if no matching key-up arrives
+/// first, the paired [`AutorepeatTimer`] re-emits the key-down event, then keeps re-emitting every
+/// `repeat_period` until the key-up arrives. Only one key repeats at a time: a new key-down
+/// replaces whatever was previously repeating.
+///
+/// # Example
+/// ```rust
+/// static SHARED: AutorepeatShared<CriticalSectionRawMutex, KeyEvent> = AutorepeatShared::new();
+/// let (mut autorepeat, mut timer) = Autorepeat::new(&SHARED, sender, initial_delay, repeat_period);
+///
+/// // `autorepeat` slots into the chain as a stage; `timer.run()` is joined alongside the rest of
+/// // the keyboard's tasks so repeats are actually emitted.
+/// embassy_futures::join::join(
+///     run_processor_chain!(chain => autorepeat, key_remap, keyboard_report_builder),
+///     timer.run(),
+/// )
+/// .await;
+/// ```
+pub struct Autorepeat<'d, M: RawMutex, E: RepeatableEvent> {
+    shared: &'d AutorepeatShared<M, E>,
+    initial_delay: Duration,
+}
+
+/// The timer half of an autorepeat pair: waits for the active key's deadline and re-emits it onto
+/// `sender` every `repeat_period`. See [`Autorepeat::new`].
+pub struct AutorepeatTimer<'d, 'ch, M: RawMutex, E: RepeatableEvent, const N: usize> {
+    shared: &'d AutorepeatShared<M, E>,
+    sender: Sender<'ch, M, E, N>,
+    repeat_period: Duration,
+}
+
+impl<'d, M: RawMutex, E: RepeatableEvent> Autorepeat<'d, M, E> {
+    /// Build an `Autorepeat`/`AutorepeatTimer` pair sharing `shared`, re-emitting onto `sender`
+    /// after `initial_delay`, then every `repeat_period`.
+    pub const fn new<'ch, const N: usize>(
+        shared: &'d AutorepeatShared<M, E>,
+        sender: Sender<'ch, M, E, N>,
+        initial_delay: Duration,
+        repeat_period: Duration,
+    ) -> (Self, AutorepeatTimer<'d, 'ch, M, E, N>) {
+        (
+            Self { shared, initial_delay },
+            AutorepeatTimer { shared, sender, repeat_period },
+        )
+    }
+}
+
+impl<'d, 'ch, M: RawMutex, E: RepeatableEvent, const N: usize> AutorepeatTimer<'d, 'ch, M, E, N> {
+    /// Drive the repeat timer, re-emitting the active key at `repeat_period` until it is
+    /// cancelled. Run this concurrently with the rest of the keyboard's tasks.
+    pub async fn run(&mut self) {
+        loop {
+            self.run_one_cycle().await;
+        }
+    }
+
+    /// Wait for (and handle) exactly one scheduler deadline: either the active key's repeat fires,
+    /// or the wait is cut short because `Autorepeat::process` scheduled a new deadline in the
+    /// meantime, in which case this returns without sending anything so the caller can recompute.
+    async fn run_one_cycle(&mut self) {
+        // Compute (and wait for) the next deadline without holding the lock for the whole wait,
+        // so `Autorepeat::process` can still promptly cancel a pending repeat (e.g. on key-up)
+        // while the timer is waiting.
+        let deadline = { self.shared.state.lock().await.scheduler.next_deadline() };
+        match deadline {
+            Some(deadline) => match select(Timer::at(deadline), self.shared.wake.wait()).await {
+                Either::First(()) => {}
+                Either::Second(()) => return, // A new deadline was scheduled; caller recomputes.
+            },
+            None => {
+                self.shared.wake.wait().await;
+                return;
+            }
+        }
+
+        let mut state = self.shared.state.lock().await;
+        let Some(event) = state.scheduler.take_due() else {
+            return;
+        };
+        if state.active.as_ref() != Some(&event.key_id()) {
+            return;
+        }
+        state.scheduler.schedule(event.clone(), self.repeat_period);
+        drop(state);
+        self.sender.send(event).await;
+    }
+}
+
+impl<'d, M: RawMutex, E: RepeatableEvent> InputProcessor for Autorepeat<'d, M, E> {
+    type EventType = E;
+    type ReportType = NoReport;
+
+    async fn process(&mut self, event: Self::EventType) -> ProcessorStatus<Self::EventType> {
+        let mut state = self.shared.state.lock().await;
+        if event.is_press() {
+            if state.active.as_ref() != Some(&event.key_id()) {
+                // A genuinely new press; any previously repeating key is implicitly replaced.
+                state.active = Some(event.key_id());
+                state.scheduler.schedule(event.clone(), self.initial_delay);
+                drop(state);
+                self.shared.wake.signal(());
+            }
+            // Else this is a repeat `AutorepeatTimer` just re-emitted for the key that's already
+            // active: it already rescheduled the next one at `repeat_period`, so leave the
+            // scheduler alone here or every repeat after the first would reset to `initial_delay`.
+        } else if state.active.as_ref() == Some(&event.key_id()) {
+            state.active = None;
+        }
+        ProcessorStatus::Forward(event)
+    }
+
+    async fn read_event(&self) -> Self::EventType {
+        // `Autorepeat` is driven as a `ProcessorChain` stage, which calls `process` directly;
+        // it has no event source of its own.
+        core::future::pending().await
+    }
+
+    async fn send_report(&self, _report: Self::ReportType) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embassy_time::{Duration, MockDriver};
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct TestEvent {
+        key: u8,
+        press: bool,
+    }
+
+    impl RepeatableEvent for TestEvent {
+        type KeyId = u8;
+
+        fn key_id(&self) -> u8 {
+            self.key
+        }
+
+        fn is_press(&self) -> bool {
+            self.press
+        }
+    }
+
+    fn noop_waker() -> Waker {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(core::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &VTABLE)) }
+    }
+
+    /// Poll `fut` until it resolves, busy-spinning. Used with the mock time driver, where a
+    /// pending `Timer` resolves as soon as the mocked clock has been advanced far enough, so this
+    /// never spins for long in these tests.
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = pin!(fut);
+        loop {
+            if let Poll::Ready(val) = fut.as_mut().poll(&mut cx) {
+                return val;
+            }
+        }
+    }
+
+    #[test]
+    fn repeats_at_steady_period_after_initial_delay() {
+        MockDriver::get().reset();
+        let shared: AutorepeatShared<NoopRawMutex, TestEvent> = AutorepeatShared::new();
+        let channel: embassy_sync::channel::Channel<NoopRawMutex, TestEvent, 4> = embassy_sync::channel::Channel::new();
+
+        let (mut autorepeat, mut timer) =
+            Autorepeat::new(&shared, channel.sender(), Duration::from_millis(200), Duration::from_millis(50));
+
+        let key_down = TestEvent { key: 1, press: true };
+        block_on(autorepeat.process(key_down.clone()));
+
+        // Before `initial_delay`, nothing has been re-emitted yet.
+        MockDriver::get().advance(Duration::from_millis(199));
+        assert!(channel.try_receive().is_err());
+
+        // `initial_delay` elapses: the timer emits the first repeat and reschedules at
+        // `repeat_period`, not back at `initial_delay`.
+        MockDriver::get().advance(Duration::from_millis(1));
+        block_on(timer.run_one_cycle());
+        assert_eq!(channel.try_receive().unwrap(), key_down);
+
+        // Only `repeat_period` (50ms), not another `initial_delay` (200ms), should be needed for
+        // the next repeat -- this is the bug `6272f73` fixed.
+        MockDriver::get().advance(Duration::from_millis(50));
+        block_on(timer.run_one_cycle());
+        assert_eq!(channel.try_receive().unwrap(), key_down);
+    }
+
+    #[test]
+    fn key_up_cancels_pending_repeat() {
+        MockDriver::get().reset();
+        let shared: AutorepeatShared<NoopRawMutex, TestEvent> = AutorepeatShared::new();
+        let channel: embassy_sync::channel::Channel<NoopRawMutex, TestEvent, 4> = embassy_sync::channel::Channel::new();
+
+        let (mut autorepeat, mut timer) =
+            Autorepeat::new(&shared, channel.sender(), Duration::from_millis(200), Duration::from_millis(50));
+
+        block_on(autorepeat.process(TestEvent { key: 1, press: true }));
+        block_on(autorepeat.process(TestEvent { key: 1, press: false }));
+
+        MockDriver::get().advance(Duration::from_millis(500));
+        block_on(timer.run_one_cycle());
+        assert!(channel.try_receive().is_err(), "a released key must not repeat");
+    }
+}
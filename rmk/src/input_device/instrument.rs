@@ -0,0 +1,139 @@
+//! Per-device latency and event-count instrumentation for RMK
+//!
+//! This module is an optional subsystem (enabled by the `device_instrument` feature) that records
+//! how long events take to travel from
+//! [`InputDevice::send_event`](super::InputDevice::send_event) to the point a report is produced.
+//! Durations are bucketed into an exponential-ish [`LatencyHistogram`] (<1ms, 1-10ms, 10-100ms,
+//! >100ms) alongside total event and drop counts, so users can diagnose scan-rate or processing
+//! bottlenecks (slow tap-hold resolution, channel backpressure) on-device instead of guessing.
+//! [`LatencyHistogram::snapshot`] is the accessor meant to be surfaced over the RPC/debug channel
+//! (see [`crate::input_device::rpc`]).
+//!
+//! Stamp an event with [`Timestamped::new`] at `send_event`, then call
+//! [`Timestamped::elapsed`] and [`LatencyHistogram::record`] at the point a report is emitted for
+//! it. [`ProcessorChain`](super::processor_chain::ProcessorChain) does exactly this for any event
+//! type that implements [`Stamped`] when built with
+//! [`ProcessorChain::with_histogram`](super::processor_chain::ProcessorChain::with_histogram):
+//! it records a hit when a stage returns `Handled` and a drop when no stage handles the event.
+//! Such a chain keeps a single histogram for everything that passes through it; it does not split
+//! the figures by input device or by stage.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use embassy_time::{Duration, Instant};
+
+/// An event tagged with the [`Instant`] it was sent, so elapsed processing time can be measured
+/// once a report is produced for it.
+#[derive(Clone)]
+pub struct Timestamped<E> {
+    event: E,
+    sent_at: Instant,
+}
+
+impl<E> Timestamped<E> {
+    /// Wrap `event`, stamping it with the current time.
+    pub fn new(event: E) -> Self {
+        Self { event, sent_at: Instant::now() }
+    }
+
+    /// The wrapped event.
+    pub fn into_inner(self) -> E {
+        self.event
+    }
+
+    /// Time elapsed since this event was stamped.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now() - self.sent_at
+    }
+}
+
+/// Implemented by event types that carry a send timestamp, so generic code can measure latency
+/// without needing to know the concrete event type underneath.
+pub trait Stamped {
+    /// Time elapsed since this value was stamped.
+    fn elapsed(&self) -> Duration;
+}
+
+impl<E> Stamped for Timestamped<E> {
+    fn elapsed(&self) -> Duration {
+        Timestamped::elapsed(self)
+    }
+}
+
+/// A snapshot of a [`LatencyHistogram`]'s counters.
+pub struct LatencyStats {
+    /// Events whose report was produced in under 1ms.
+    pub sub_millis: u32,
+    /// Events that took 1ms to 10ms.
+    pub one_to_ten_millis: u32,
+    /// Events that took 10ms to 100ms.
+    pub ten_to_hundred_millis: u32,
+    /// Events that took over 100ms.
+    pub over_hundred_millis: u32,
+    /// Total events recorded, across all buckets.
+    pub events: u32,
+    /// Events that were dropped before a report could be produced for them.
+    pub drops: u32,
+}
+
+/// An exponential-ish latency histogram plus event and drop counters, updated atomically so it can
+/// be shared between the task producing events and whatever reads the stats out.
+pub struct LatencyHistogram {
+    sub_millis: AtomicU32,
+    one_to_ten_millis: AtomicU32,
+    ten_to_hundred_millis: AtomicU32,
+    over_hundred_millis: AtomicU32,
+    events: AtomicU32,
+    drops: AtomicU32,
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram.
+    pub const fn new() -> Self {
+        Self {
+            sub_millis: AtomicU32::new(0),
+            one_to_ten_millis: AtomicU32::new(0),
+            ten_to_hundred_millis: AtomicU32::new(0),
+            over_hundred_millis: AtomicU32::new(0),
+            events: AtomicU32::new(0),
+            drops: AtomicU32::new(0),
+        }
+    }
+
+    /// Record that an event took `elapsed` to travel from `send_event` to its report.
+    pub fn record(&self, elapsed: Duration) {
+        let bucket = if elapsed < Duration::from_millis(1) {
+            &self.sub_millis
+        } else if elapsed < Duration::from_millis(10) {
+            &self.one_to_ten_millis
+        } else if elapsed < Duration::from_millis(100) {
+            &self.ten_to_hundred_millis
+        } else {
+            &self.over_hundred_millis
+        };
+        bucket.fetch_add(1, Ordering::Relaxed);
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that an event was dropped before a report could be produced for it.
+    pub fn record_drop(&self) {
+        self.drops.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Read out the current counters.
+    pub fn snapshot(&self) -> LatencyStats {
+        LatencyStats {
+            sub_millis: self.sub_millis.load(Ordering::Relaxed),
+            one_to_ten_millis: self.one_to_ten_millis.load(Ordering::Relaxed),
+            ten_to_hundred_millis: self.ten_to_hundred_millis.load(Ordering::Relaxed),
+            over_hundred_millis: self.over_hundred_millis.load(Ordering::Relaxed),
+            events: self.events.load(Ordering::Relaxed),
+            drops: self.drops.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
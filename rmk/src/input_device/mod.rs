@@ -8,7 +8,17 @@
 use core::future::Future;
 use usbd_hid::descriptor::AsInputReport;
 
+pub mod autorepeat;
+#[cfg(feature = "device_instrument")]
+pub mod instrument;
+pub mod processor_chain;
+#[cfg(feature = "alloc")]
+pub mod registry;
 pub mod rotary_encoder;
+#[cfg(feature = "device_rpc")]
+pub mod rpc;
+pub mod scheduler;
+pub mod virtual_device;
 
 /// The trait for input devices.
 ///
@@ -53,6 +63,22 @@ pub trait InputDevice {
     fn send_event(&mut self, event: Self::EventType) -> impl Future<Output = ()>;
 }
 
+/// Outcome of [`InputProcessor::process`] for a single event.
+///
+/// This status is what makes a processor usable as a stage in a [`processor_chain::ProcessorChain`]:
+/// it tells the chain whether to stop propagating the event, forward a (possibly rewritten) event
+/// to the next stage, or let the next stage see the original event untouched. A processor that is
+/// only ever run standalone via [`InputProcessor::run`] can freely return `Unhandled` once it is
+/// done with an event, since there is no downstream stage to forward to.
+pub enum ProcessorStatus<E> {
+    /// The event was consumed by this processor; later stages must not see it.
+    Handled,
+    /// The event, possibly rewritten, should be passed to the next stage.
+    Forward(E),
+    /// This processor did not act on the event; the original event continues downstream unchanged.
+    Unhandled,
+}
+
 /// The trait for input processors.
 ///
 /// The input processor processes the [`Event`] from the input devices and converts it to the final HID report.
@@ -71,8 +97,11 @@ pub trait InputProcessor {
     ///
     /// Note there might be mulitple HID reports are generated for one event,
     /// so the "sending report" operation should be done in the `process` method.
-    /// The input processor implementor should be aware of this.  
-    fn process(&mut self, event: Self::EventType) -> impl Future<Output = ()>;
+    /// The input processor implementor should be aware of this.
+    ///
+    /// The returned [`ProcessorStatus`] only matters when this processor is used as a stage of a
+    /// [`processor_chain::ProcessorChain`]; [`InputProcessor::run`] ignores it.
+    fn process(&mut self, event: Self::EventType) -> impl Future<Output = ProcessorStatus<Self::EventType>>;
 
     /// Get the input event.
     ///
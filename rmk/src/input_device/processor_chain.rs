@@ -0,0 +1,153 @@
+//! Processor chain for RMK
+//!
+//! This module defines [`ProcessorChain`], a pipeline that wires [`InputProcessor`](super::InputProcessor)s
+//! into an ordered sequence so that one stage can transform, filter, or swallow an event before the
+//! next stage ever sees it. This is the same shape as the input-handler pipelines found in other
+//! input stacks: events flow through an ordered chain and any handler may consume or rewrite them.
+//!
+//! Use the [`run_processor_chain!`] macro to build and run a chain; it is the sequential counterpart
+//! of [`run_processors!`](crate::run_processors), which runs processors concurrently instead.
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::channel::Receiver;
+use serde::Serialize;
+use usbd_hid::descriptor::AsInputReport;
+
+#[cfg(feature = "device_instrument")]
+use super::instrument::{LatencyHistogram, Stamped};
+
+/// An ordered pipeline of input processors sharing a single upstream event channel.
+///
+/// `ProcessorChain` owns the receiving end of the channel that feeds the pipeline. For every event
+/// pulled off the channel, the [`run_processor_chain!`] macro feeds it to the first processor, then
+/// the second, and so on, stopping as soon as a processor returns
+/// [`ProcessorStatus::Handled`](super::ProcessorStatus::Handled). A processor may also rewrite the
+/// event for the remaining stages with
+/// [`ProcessorStatus::Forward`](super::ProcessorStatus::Forward), or decline to touch it with
+/// [`ProcessorStatus::Unhandled`](super::ProcessorStatus::Unhandled), in which case the original
+/// event is passed on unchanged.
+///
+/// This struct only holds the channel (and, with the `device_instrument` feature, an optional
+/// latency histogram); the processors themselves are supplied directly to
+/// [`run_processor_chain!`] and are not stored here, since they are typically of different concrete
+/// types.
+///
+/// # Example
+/// ```rust
+/// // `LayerTapResolver`, `KeyRemap` and `KeyboardReportBuilder` should implement `InputProcessor`
+/// // with a shared `EventType`.
+/// let chain = ProcessorChain::new(receiver);
+/// run_processor_chain!(chain => layer_tap_resolver, key_remap, keyboard_report_builder).await;
+/// ```
+pub struct ProcessorChain<'ch, M: RawMutex, E, const N: usize> {
+    receiver: Receiver<'ch, M, E, N>,
+    #[cfg(feature = "device_instrument")]
+    histogram: Option<&'ch LatencyHistogram>,
+}
+
+impl<'ch, M: RawMutex, E: Clone, const N: usize> ProcessorChain<'ch, M, E, N> {
+    /// Create a new chain that reads its events from `receiver`.
+    pub fn new(receiver: Receiver<'ch, M, E, N>) -> Self {
+        Self {
+            receiver,
+            #[cfg(feature = "device_instrument")]
+            histogram: None,
+        }
+    }
+
+    /// Wait for the next event on the upstream channel.
+    pub async fn next_event(&self) -> E {
+        self.receiver.receive().await
+    }
+}
+
+#[cfg(feature = "device_instrument")]
+impl<'ch, M: RawMutex, E: Clone + Stamped, const N: usize> ProcessorChain<'ch, M, E, N> {
+    /// Create a chain that also records per-event latency into `histogram`: a hit (time since the
+    /// event was stamped) when a stage returns `Handled`, a drop when no stage handles the event.
+    pub fn with_histogram(receiver: Receiver<'ch, M, E, N>, histogram: &'ch LatencyHistogram) -> Self {
+        Self {
+            receiver,
+            histogram: Some(histogram),
+        }
+    }
+
+    /// The latency/event-count stats recorded so far, if this chain was built with
+    /// [`Self::with_histogram`].
+    pub fn stats(&self) -> Option<super::instrument::LatencyStats> {
+        self.histogram.map(LatencyHistogram::snapshot)
+    }
+
+    /// Record that an event reached the end of the chain as `Handled`, i.e. a report was produced
+    /// for it.
+    fn record_handled(&self, event: &E) {
+        if let Some(histogram) = self.histogram {
+            histogram.record(event.elapsed());
+        }
+    }
+
+    /// Record that an event fell off the end of the chain without any stage handling it.
+    fn record_drop(&self) {
+        if let Some(histogram) = self.histogram {
+            histogram.record_drop();
+        }
+    }
+}
+
+/// Macro to build and run a [`ProcessorChain`] over a list of processors.
+///
+/// The chain reads one event at a time from `$chain` and threads it through every processor in
+/// order, stopping early when a processor returns `ProcessorStatus::Handled`. This macro never
+/// resolves; like [`run_processors!`](crate::run_processors), it is meant to be joined alongside
+/// the rest of the keyboard's tasks.
+///
+/// # Note
+/// Every processor passed to this macro must share the same `EventType` as `$chain`, since the
+/// (possibly rewritten) event is threaded directly from one stage to the next.
+///
+/// # Example
+/// ```rust
+/// run_processor_chain!(chain => layer_tap_resolver, key_remap, keyboard_report_builder).await;
+/// ```
+#[macro_export]
+macro_rules! run_processor_chain {
+    ($chain:expr => $($processor:expr),+ $(,)?) => {
+        async {
+            loop {
+                let event = $chain.next_event().await;
+                $crate::run_processor_chain!(@step $chain, event => $($processor),+);
+            }
+        }
+    };
+    (@step $chain:expr, $event:expr => $first:expr $(, $rest:expr)*) => {
+        match $first.process($event.clone()).await {
+            $crate::input_device::ProcessorStatus::Handled => {
+                #[cfg(feature = "device_instrument")]
+                $chain.record_handled(&$event);
+            }
+            $crate::input_device::ProcessorStatus::Forward(next_event) => {
+                $crate::run_processor_chain!(@step $chain, next_event => $($rest),*);
+            }
+            $crate::input_device::ProcessorStatus::Unhandled => {
+                $crate::run_processor_chain!(@step $chain, $event => $($rest),*);
+            }
+        }
+    };
+    (@step $chain:expr, $event:expr =>) => {
+        #[cfg(feature = "device_instrument")]
+        $chain.record_drop();
+        let _ = $event;
+    };
+}
+
+/// A placeholder [`InputProcessor::ReportType`](super::InputProcessor::ReportType) for chain
+/// stages that only transform or filter events and never produce a HID report themselves, such as
+/// a layer-tap resolver or [`Autorepeat`](super::autorepeat::Autorepeat).
+///
+/// `send_report` is never meaningfully called for such a stage, since its `process` always
+/// resolves to [`ProcessorStatus::Handled`](super::ProcessorStatus::Handled) or
+/// [`ProcessorStatus::Forward`](super::ProcessorStatus::Forward).
+#[derive(Serialize)]
+pub struct NoReport;
+
+impl AsInputReport for NoReport {}
@@ -0,0 +1,106 @@
+//! Runtime device registry with hotplug add/remove for RMK
+//!
+//! [`run_devices!`](crate::run_devices) fixes the set of devices at compile time by building one
+//! joined future. `DeviceRegistry` instead owns a dynamic collection of boxed [`InputDevice`] tasks
+//! that can be added or removed while the keyboard runs. It does not detect devices itself:
+//! whatever notices a hot-attached peripheral registers it with [`DeviceRegistry::add`]. This is
+//! what lets RMK support a hot-attached peripheral over a split link, or a
+//! detachable encoder/trackpad module, without a fixed compile-time device list.
+//!
+//! Every registered device still sends its events into whatever channel it was constructed with
+//! (the same shared processor-input channel any other device would use), so `DeviceRegistry` only
+//! needs to drive each device's `run` task; it does not multiplex events itself.
+//!
+//! This module requires the `alloc` feature: erasing each device's concrete type so a dynamically
+//! sized pool of them can be polled together requires boxing its `run` future.
+
+use alloc::boxed::Box;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::Poll;
+
+use heapless::Vec;
+
+use super::InputDevice;
+
+/// A type-erased, pinned device task: a device's `run` future, boxed so the registry can poll
+/// devices of different concrete types together.
+struct Slot<'d> {
+    future: Pin<Box<dyn Future<Output = ()> + 'd>>,
+}
+
+/// A handle to a device registered with a [`DeviceRegistry`], used to remove it later.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct DeviceHandle(usize);
+
+/// A dynamic, bounded-capacity collection of input device tasks.
+///
+/// `N` bounds how many devices may be registered at once; this is a fixed-capacity, no-alloc-pool
+/// design (only the individual task futures are boxed) consistent with the rest of RMK's input
+/// pipeline.
+pub struct DeviceRegistry<'d, const N: usize> {
+    slots: Vec<Option<Slot<'d>>, N>,
+}
+
+impl<'d, const N: usize> DeviceRegistry<'d, N> {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self { slots: Vec::new() }
+    }
+
+    /// Register `device` and start running its task.
+    ///
+    /// Returns `None` if the registry is already at capacity `N`.
+    pub fn add<D>(&mut self, mut device: D) -> Option<DeviceHandle>
+    where
+        D: InputDevice + 'd,
+    {
+        let future: Pin<Box<dyn Future<Output = ()> + 'd>> = Box::pin(async move { device.run().await });
+        if let Some(i) = self.slots.iter().position(|slot| slot.is_none()) {
+            self.slots[i] = Some(Slot { future });
+            return Some(DeviceHandle(i));
+        }
+        self.slots.push(Some(Slot { future })).ok()?;
+        Some(DeviceHandle(self.slots.len() - 1))
+    }
+
+    /// Stop and tear down a previously registered device's task.
+    pub fn remove(&mut self, handle: DeviceHandle) {
+        if let Some(slot) = self.slots.get_mut(handle.0) {
+            *slot = None;
+        }
+    }
+
+    /// Drive every registered device's task concurrently.
+    ///
+    /// Join this alongside the rest of the keyboard's tasks (e.g. with
+    /// [`embassy_futures::join::join`]). This future never resolves and borrows the registry
+    /// mutably while it is polled, so [`add`](Self::add) and [`remove`](Self::remove) take effect
+    /// once it has been dropped (e.g. after losing an `embassy_futures::select::select`) and `run`
+    /// is called again. Device tasks are stored in the registry, not in this future, so dropping
+    /// it pauses them without tearing them down.
+    pub async fn run(&mut self) {
+        core::future::poll_fn(|cx| {
+            for i in 0..self.slots.len() {
+                // An `InputDevice::run` loop is expected to run forever, so a slot that resolves
+                // to `Ready(())` is a device that exited on its own (e.g. on disconnect); clear it
+                // so it isn't polled again, which would violate the `Future` contract and panic.
+                let done = match &mut self.slots[i] {
+                    Some(slot) => slot.future.as_mut().poll(cx).is_ready(),
+                    None => false,
+                };
+                if done {
+                    self.slots[i] = None;
+                }
+            }
+            Poll::<()>::Pending
+        })
+        .await
+    }
+}
+
+impl<'d, const N: usize> Default for DeviceRegistry<'d, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
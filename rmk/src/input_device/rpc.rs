@@ -0,0 +1,148 @@
+//! Host-side device query/config over postcard-rpc
+//!
+//! This module is an optional subsystem (enabled by the `device_rpc` feature) that exposes input
+//! devices over a `postcard`-based RPC endpoint on the existing USB connection. A host tool can use
+//! it to enumerate attached input devices, read their current configuration (e.g. rotary-encoder
+//! resolution, autorepeat timings), and push new settings at runtime without reflashing. This turns
+//! static compile-time device parameters into a live-tunable surface, which is valuable for
+//! iterating on tap-hold timings and encoder sensitivity.
+//!
+//! Devices opt in by implementing [`RpcConfigurable`] and registering with a [`DeviceRpc`]; the
+//! firmware side then runs [`DeviceRpc::run`] as an additional task, joined into the main loop
+//! alongside [`run_devices!`](crate::run_devices) and [`run_processors!`](crate::run_processors).
+
+use heapless::Vec;
+use postcard::{from_bytes, to_slice};
+use serde::{Deserialize, Serialize};
+
+/// Identifies a registered device within a [`DeviceRpc`] endpoint.
+pub type DeviceId = u8;
+
+/// A request sent by the host tool.
+#[derive(Serialize, Deserialize)]
+pub enum RpcRequest<'a> {
+    /// List the ids of all registered devices.
+    ListDevices,
+    /// Read the current configuration of a device, as opaque postcard-encoded bytes.
+    GetConfig(DeviceId),
+    /// Overwrite a device's configuration with the given postcard-encoded bytes.
+    SetConfig(DeviceId, &'a [u8]),
+}
+
+/// The firmware's response to an [`RpcRequest`].
+#[derive(Serialize, Deserialize)]
+pub enum RpcResponse<'a> {
+    /// The ids of all registered devices, in registration order.
+    Devices(&'a [DeviceId]),
+    /// A device's current configuration, as opaque postcard-encoded bytes. Empty in reply to a
+    /// [`RpcRequest::SetConfig`] that was applied.
+    Config(&'a [u8]),
+    /// The device id in a request did not match any registered device, or the request could not
+    /// be decoded.
+    UnknownDevice,
+}
+
+/// The transport that carries [`RpcRequest`]/[`RpcResponse`] bytes over the existing USB
+/// connection.
+///
+/// `DeviceRpc` is deliberately agnostic to which USB class actually moves the bytes (e.g. a
+/// vendor-defined interface); implement this trait for that transport so [`DeviceRpc::run`] can
+/// drive it.
+pub trait RpcTransport {
+    /// Read the next request into `buf`, returning the number of bytes read.
+    fn read_request(&mut self, buf: &mut [u8]) -> impl core::future::Future<Output = usize>;
+
+    /// Write a response back to the host.
+    fn write_response(&mut self, bytes: &[u8]) -> impl core::future::Future<Output = ()>;
+}
+
+/// A device whose configuration can be queried and updated at runtime over [`DeviceRpc`].
+///
+/// Implementors serialize their own configuration type with `postcard`; `DeviceRpc` only ever sees
+/// opaque bytes, so devices are free to use whatever configuration shape suits them (a rotary
+/// encoder's resolution, an autorepeat stage's delay and period, and so on).
+pub trait RpcConfigurable {
+    /// Encode the current configuration into `buf`, returning the bytes written.
+    fn get_config<'b>(&self, buf: &'b mut [u8]) -> &'b [u8];
+
+    /// Decode `bytes` and apply it as the new configuration.
+    fn set_config(&mut self, bytes: &[u8]);
+}
+
+/// An RPC endpoint that multiplexes requests to up to `N` registered devices.
+///
+/// `DeviceRpc` does not own a transport; callers feed it request bytes received over USB and send
+/// the response bytes it produces back to the host. This keeps the module agnostic to whichever
+/// USB class carries the RPC traffic.
+pub struct DeviceRpc<'d, const N: usize> {
+    devices: Vec<&'d mut dyn RpcConfigurable, N>,
+}
+
+impl<'d, const N: usize> DeviceRpc<'d, N> {
+    /// Create an empty RPC endpoint.
+    pub fn new() -> Self {
+        Self { devices: Vec::new() }
+    }
+
+    /// Register a device, returning its assigned [`DeviceId`].
+    ///
+    /// Returns `None` if the endpoint is already at capacity `N`.
+    pub fn register(&mut self, device: &'d mut dyn RpcConfigurable) -> Option<DeviceId> {
+        let id = self.devices.len() as DeviceId;
+        self.devices.push(device).ok()?;
+        Some(id)
+    }
+
+    /// The largest config payload a single device may report through [`Self::handle`].
+    const CONFIG_SCRATCH: usize = 64;
+
+    /// Decode one request and encode its response into `out`, returning the bytes written.
+    pub fn handle(&mut self, request: &[u8], out: &mut [u8]) -> usize {
+        // Device ids and config bytes are staged in scratch buffers, separate from `out`, since
+        // the response borrows from whichever one it was built from while `out` is later
+        // borrowed mutably again to encode that response.
+        let mut ids: Vec<DeviceId, N> = Vec::new();
+        let mut config = [0u8; Self::CONFIG_SCRATCH];
+
+        let response = match from_bytes::<RpcRequest>(request) {
+            Ok(RpcRequest::ListDevices) => {
+                for i in 0..self.devices.len() {
+                    let _ = ids.push(i as DeviceId);
+                }
+                RpcResponse::Devices(&ids)
+            }
+            Ok(RpcRequest::GetConfig(id)) => match self.devices.get(id as usize) {
+                Some(device) => RpcResponse::Config(device.get_config(&mut config)),
+                None => RpcResponse::UnknownDevice,
+            },
+            Ok(RpcRequest::SetConfig(id, bytes)) => match self.devices.get_mut(id as usize) {
+                Some(device) => {
+                    device.set_config(bytes);
+                    RpcResponse::Config(&[])
+                }
+                None => RpcResponse::UnknownDevice,
+            },
+            Err(_) => RpcResponse::UnknownDevice,
+        };
+        to_slice(&response, out).map(|s| s.len()).unwrap_or(0)
+    }
+
+    /// Run as a task: repeatedly read a request from `transport`, dispatch it via [`Self::handle`],
+    /// and write back the response. Join this alongside the rest of the keyboard's tasks, e.g.
+    /// [`run_devices!`](crate::run_devices) and [`run_processors!`](crate::run_processors).
+    pub async fn run(&mut self, transport: &mut impl RpcTransport) {
+        let mut request = [0u8; Self::CONFIG_SCRATCH];
+        let mut response = [0u8; Self::CONFIG_SCRATCH];
+        loop {
+            let len = transport.read_request(&mut request).await;
+            let len = self.handle(&request[..len], &mut response);
+            transport.write_response(&response[..len]).await;
+        }
+    }
+}
+
+impl<'d, const N: usize> Default for DeviceRpc<'d, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
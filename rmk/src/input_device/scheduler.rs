@@ -0,0 +1,210 @@
+//! Scheduled/deferred event emission for RMK
+//!
+//! This module lets an [`InputProcessor`](super::InputProcessor) enqueue an event to be emitted at
+//! a future instant instead of immediately, via [`EventScheduler`]. This is what makes tap-hold
+//! resolution and other timed key behaviors (home-row mods, one-shot layer timeouts) practical:
+//! without it, `process` is purely reactive to incoming events and has no way to say "act on this
+//! later, unless something cancels it first".
+//!
+//! A scheduled event is identified by a [`ScheduleToken`] returned from [`EventScheduler::schedule`],
+//! which can be passed back to [`EventScheduler::cancel`] before the deadline elapses (e.g. when a
+//! following key-up cancels a pending hold). Each slot carries a generation counter so a stale
+//! token -- one whose slot already fired or was cancelled and later reused for an unrelated event --
+//! is inert rather than cancelling whatever now occupies that slot. [`EventScheduler::wait_due`]
+//! resolves with the event whose deadline comes first, so a processor's `run` loop can race it
+//! against [`InputProcessor::read_event`] with [`embassy_futures::select`] and wake exactly at the
+//! nearest deadline rather than busy-polling.
+
+use embassy_time::{Duration, Instant, Timer};
+use heapless::Vec;
+
+/// An event queued for emission once `deadline` elapses.
+pub struct ScheduledEvent<E> {
+    event: E,
+    deadline: Instant,
+}
+
+impl<E> ScheduledEvent<E> {
+    /// Schedule `event` to fire `delay` from now.
+    pub fn new(event: E, delay: Duration) -> Self {
+        Self {
+            event,
+            deadline: Instant::now() + delay,
+        }
+    }
+}
+
+/// A handle to a pending [`ScheduledEvent`], used to cancel it before its deadline elapses.
+///
+/// Carries the generation the slot was at when this token was issued, so cancelling with a stale
+/// token (one whose slot already fired, was cancelled, and was later reused for a different event)
+/// is a no-op instead of cancelling the unrelated event now occupying that slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScheduleToken(usize, u32);
+
+/// One slot in an [`EventScheduler`]'s queue: the pending event, if any, and a generation counter
+/// bumped every time the slot is (re)filled.
+struct Slot<E> {
+    event: Option<ScheduledEvent<E>>,
+    generation: u32,
+}
+
+impl<E> Slot<E> {
+    const fn empty() -> Self {
+        Self { event: None, generation: 0 }
+    }
+
+    fn fill(&mut self, scheduled: ScheduledEvent<E>) -> ScheduleToken {
+        self.event = Some(scheduled);
+        self.generation = self.generation.wrapping_add(1);
+        ScheduleToken(0, self.generation)
+    }
+}
+
+/// A small time-ordered queue of pending events, to be polled alongside a processor's event channel.
+///
+/// `N` bounds how many events may be pending at once; this is a fixed-capacity, no-alloc queue like
+/// the rest of RMK's input pipeline.
+pub struct EventScheduler<E, const N: usize> {
+    slots: Vec<Slot<E>, N>,
+}
+
+impl<E, const N: usize> EventScheduler<E, N> {
+    /// Create an empty scheduler.
+    pub const fn new() -> Self {
+        Self { slots: Vec::new() }
+    }
+
+    /// Queue `event` to be emitted `delay` from now, returning a token that can cancel it.
+    ///
+    /// If the queue is full, the event with the *latest* deadline is evicted to make room: it's
+    /// the least time-critical pending entry, whereas the one due soonest is usually an
+    /// already-committed decision (e.g. a hold/timeout) that must not be silently dropped.
+    pub fn schedule(&mut self, event: E, delay: Duration) -> ScheduleToken {
+        let scheduled = ScheduledEvent::new(event, delay);
+        if let Some(i) = self.slots.iter().position(|slot| slot.event.is_none()) {
+            let ScheduleToken(_, generation) = self.slots[i].fill(scheduled);
+            return ScheduleToken(i, generation);
+        }
+        if self.slots.push(Slot::empty()).is_ok() {
+            let i = self.slots.len() - 1;
+            let ScheduleToken(_, generation) = self.slots[i].fill(scheduled);
+            return ScheduleToken(i, generation);
+        }
+        let evict = self.latest_index().unwrap_or(0);
+        let ScheduleToken(_, generation) = self.slots[evict].fill(scheduled);
+        ScheduleToken(evict, generation)
+    }
+
+    /// Cancel a previously scheduled event, if it hasn't fired and `token` is still current for
+    /// its slot (i.e. the slot hasn't since been cleared and reused for a different event).
+    pub fn cancel(&mut self, token: ScheduleToken) {
+        if let Some(slot) = self.slots.get_mut(token.0) {
+            if slot.generation == token.1 {
+                slot.event = None;
+            }
+        }
+    }
+
+    /// The index of the event with the soonest deadline, if any is pending.
+    fn earliest_index(&self) -> Option<usize> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.event.as_ref().map(|s| (i, s.deadline)))
+            .min_by_key(|&(_, deadline)| deadline)
+            .map(|(i, _)| i)
+    }
+
+    /// The index of the event with the furthest-out deadline, if any is pending.
+    fn latest_index(&self) -> Option<usize> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.event.as_ref().map(|s| (i, s.deadline)))
+            .max_by_key(|&(_, deadline)| deadline)
+            .map(|(i, _)| i)
+    }
+
+    /// The deadline of the next event to fire, if any is pending.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.earliest_index().map(|i| self.slots[i].event.as_ref().unwrap().deadline)
+    }
+
+    /// Take the next event if its deadline has already elapsed, without waiting.
+    pub fn take_due(&mut self) -> Option<E> {
+        let i = self.earliest_index()?;
+        if self.slots[i].event.as_ref()?.deadline <= Instant::now() {
+            self.slots[i].event.take().map(|scheduled| scheduled.event)
+        } else {
+            None
+        }
+    }
+
+    /// Wait until the next scheduled event's deadline elapses, then return it.
+    ///
+    /// Resolves immediately if a deadline has already passed. If nothing is scheduled, this future
+    /// never resolves, so it is meant to be raced with other work via `embassy_futures::select`.
+    pub async fn wait_due(&mut self) -> E {
+        loop {
+            match self.next_deadline() {
+                Some(deadline) => {
+                    Timer::at(deadline).await;
+                    if let Some(event) = self.take_due() {
+                        return event;
+                    }
+                    // Deadline moved or event was cancelled/replaced; keep waiting.
+                }
+                None => core::future::pending::<()>().await,
+            }
+        }
+    }
+}
+
+impl<E, const N: usize> Default for EventScheduler<E, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_evicts_latest_deadline_not_earliest() {
+        let mut scheduler: EventScheduler<&'static str, 2> = EventScheduler::new();
+        scheduler.schedule("soon", Duration::from_millis(10));
+        scheduler.schedule("far", Duration::from_millis(1000));
+
+        // Queue is already at capacity (N = 2); this third entry must evict "far" (the latest
+        // deadline), not "soon" (the most time-critical one).
+        scheduler.schedule("newest", Duration::from_millis(100));
+
+        let remaining: Vec<&'static str, 2> = scheduler
+            .slots
+            .iter()
+            .filter_map(|slot| slot.event.as_ref().map(|s| s.event))
+            .collect();
+        assert!(remaining.contains(&"soon"), "the most time-critical entry must survive eviction");
+        assert!(!remaining.contains(&"far"), "the least time-critical entry should be evicted");
+    }
+
+    #[test]
+    fn cancel_is_a_no_op_for_a_stale_token() {
+        let mut scheduler: EventScheduler<&'static str, 1> = EventScheduler::new();
+        let stale = scheduler.schedule("first", Duration::from_millis(10));
+
+        // "first" fires (or is cancelled) and the now-empty slot is reused for "second".
+        scheduler.cancel(stale);
+        let current = scheduler.schedule("second", Duration::from_millis(10));
+        assert_ne!(stale, current, "reusing a slot must bump its generation");
+
+        // Cancelling with the stale token must not remove "second".
+        scheduler.cancel(stale);
+        assert!(scheduler.slots[0].event.is_some(), "a stale token must not cancel a reused slot");
+
+        scheduler.cancel(current);
+        assert!(scheduler.slots[0].event.is_none());
+    }
+}
@@ -0,0 +1,166 @@
+//! Synthetic/virtual input device for RMK
+//!
+//! This module provides [`VirtualInputDevice`], an [`InputDevice`] that replays a fixed script of
+//! events instead of reading real hardware. This gives two concrete wins: recorded keyboard macros
+//! (capture a sequence of matrix events and replay them on a trigger), and deterministic
+//! integration testing of a processor chain without physical hardware. It interoperates with
+//! [`run_devices!`](crate::run_devices) exactly like a physical device, so no processor code needs
+//! to change to use it.
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::channel::Sender;
+use embassy_time::{Duration, Timer};
+
+use super::InputDevice;
+
+/// An [`InputDevice`] that replays a recorded `(event, delay)` script instead of sampling hardware.
+///
+/// Each event in the script is sent, then the device waits the paired `Duration` before sending
+/// the next one. When `looping` is `true`, the script restarts from the beginning after the last
+/// event; otherwise the device idles forever once the script is exhausted.
+///
+/// # Example
+/// ```rust
+/// // Replay a single key tap (key-down, then key-up 50ms later), once.
+/// let mut device = VirtualInputDevice::new(
+///     sender,
+///     [
+///         (key_down_event, Duration::from_millis(50)),
+///         (key_up_event, Duration::from_millis(50)),
+///     ],
+///     false,
+/// );
+///
+/// run_devices!(device).await;
+/// ```
+pub struct VirtualInputDevice<'ch, M: RawMutex, E: Clone, const N: usize, const S: usize> {
+    sender: Sender<'ch, M, E, N>,
+    script: [(E, Duration); S],
+    looping: bool,
+}
+
+impl<'ch, M: RawMutex, E: Clone, const N: usize, const S: usize> VirtualInputDevice<'ch, M, E, N, S> {
+    /// Create a virtual device that replays `script` on `sender`.
+    ///
+    /// If `looping` is `true`, the script restarts from the beginning once exhausted; otherwise
+    /// the device stops emitting events after the last one.
+    ///
+    /// # Panics
+    /// Panics if `looping` is `true` and `script` is empty: `run` would otherwise spin forever
+    /// restarting an empty script with no `.await` point, starving every other task.
+    pub const fn new(sender: Sender<'ch, M, E, N>, script: [(E, Duration); S], looping: bool) -> Self {
+        assert!(!looping || S > 0, "VirtualInputDevice: a looping device needs a non-empty script");
+        Self { sender, script, looping }
+    }
+}
+
+impl<'ch, M: RawMutex, E: Clone, const N: usize, const S: usize> InputDevice for VirtualInputDevice<'ch, M, E, N, S> {
+    type EventType = E;
+
+    async fn run(&mut self) {
+        loop {
+            for (event, delay) in self.script.iter().cloned() {
+                self.send_event(event).await;
+                Timer::after(delay).await;
+            }
+            if !self.looping {
+                core::future::pending::<()>().await;
+            }
+        }
+    }
+
+    async fn send_event(&mut self, event: Self::EventType) {
+        self.sender.send(event).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embassy_sync::channel::Channel;
+    use heapless::Vec;
+
+    use super::*;
+    use crate::input_device::processor_chain::{NoReport, ProcessorChain};
+    use crate::input_device::{InputProcessor, ProcessorStatus};
+
+    #[derive(Clone)]
+    struct TestEvent(u8);
+
+    /// A chain stage that records every event it sees and forwards it unchanged, so a test can
+    /// assert on what actually made it through the chain.
+    struct Recorder<'r> {
+        seen: &'r RefCell<Vec<u8, 8>>,
+    }
+
+    impl<'r> InputProcessor for Recorder<'r> {
+        type EventType = TestEvent;
+        type ReportType = NoReport;
+
+        async fn process(&mut self, event: Self::EventType) -> ProcessorStatus<Self::EventType> {
+            self.seen.borrow_mut().push(event.0).ok();
+            ProcessorStatus::Forward(event)
+        }
+
+        async fn read_event(&self) -> Self::EventType {
+            core::future::pending().await
+        }
+
+        async fn send_report(&self, _report: Self::ReportType) {}
+    }
+
+    fn noop_waker() -> Waker {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(core::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &VTABLE)) }
+    }
+
+    /// Poll `fut` until it resolves, busy-spinning. Every future driven here resolves as soon as
+    /// it's polled once (no real timer wait is involved), so this never spins.
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = pin!(fut);
+        loop {
+            if let Poll::Ready(val) = fut.as_mut().poll(&mut cx) {
+                return val;
+            }
+        }
+    }
+
+    #[test]
+    fn events_flow_from_the_virtual_device_through_the_chain() {
+        let channel: Channel<NoopRawMutex, TestEvent, 4> = Channel::new();
+        // `VirtualInputDevice::run` sends each scripted event then awaits a delay before the next;
+        // driving that delay needs a real executor, so this test calls `send_event` directly for
+        // each scripted event instead -- exactly what `run` does between delays -- which still
+        // exercises the device and `ProcessorChain` working together over the shared channel.
+        let mut device = VirtualInputDevice::new(
+            channel.sender(),
+            [(TestEvent(1), Duration::from_millis(10)), (TestEvent(2), Duration::from_millis(10))],
+            false,
+        );
+        let chain = ProcessorChain::new(channel.receiver());
+        let seen = RefCell::new(Vec::<u8, 8>::new());
+        let mut recorder = Recorder { seen: &seen };
+
+        block_on(device.send_event(TestEvent(1)));
+        block_on(device.send_event(TestEvent(2)));
+        block_on(async {
+            for _ in 0..2 {
+                let event = chain.next_event().await;
+                recorder.process(event).await;
+            }
+        });
+
+        assert_eq!(seen.borrow().as_slice(), &[1, 2]);
+    }
+}